@@ -1,4 +1,6 @@
 use napi_derive::napi;
+use std::collections::HashMap;
+use unicode_normalization::char::{decompose_canonical, is_combining_mark};
 
 #[napi(object)]
 pub struct NoteInput {
@@ -15,6 +17,34 @@ pub struct NoteInput {
     pub hidden: bool,
 }
 
+/// How a query atom should be matched against the title.
+///
+/// `Plain` and `ExactSubstring` are also checked against the path. Every kind
+/// except `ExactSubstring` is eligible for fuzzy matching.
+#[napi(string_enum)]
+#[derive(Debug, PartialEq, Eq)]
+pub enum QueryAtomKind {
+    Plain,
+    PrefixAnchor,
+    SuffixAnchor,
+    ExactAnchor,
+    ExactSubstring,
+}
+
+/// A single classified piece of a raw search query, e.g. `^foo`, `!bar`, `'baz`.
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct QueryAtom {
+    #[napi(js_name = "kind")]
+    pub kind: QueryAtomKind,
+
+    #[napi(js_name = "inverse")]
+    pub inverse: bool,
+
+    #[napi(js_name = "text")]
+    pub text: String,
+}
+
 #[napi(object)]
 pub struct ScoreParams {
     #[napi(js_name = "query")]
@@ -25,89 +55,600 @@ pub struct ScoreParams {
 
     #[napi(js_name = "normalizedQuery")]
     pub normalized_query: String,
+
+    /// When present and non-empty, the structured fzf/skim-style query takes
+    /// over title/path matching from `normalizedQuery`/`tokens`. `query` is
+    /// still used for the note-id match, and `tokens` only supplies the BM25
+    /// idf of an atom whose text equals one of them.
+    #[napi(js_name = "atoms")]
+    pub atoms: Option<Vec<QueryAtom>>,
+
+    /// Corpus statistics enabling BM25 term weighting of `tokens`. When
+    /// absent, token matches fall back to the flat per-token factors.
+    #[napi(js_name = "corpusStats")]
+    pub corpus_stats: Option<CorpusStats>,
+
+    /// When `true`, a token that only matches a chunk case-insensitively is
+    /// rejected outright instead of scoring with [`CASE_MISMATCH_PENALTY`].
+    /// Defaults to `false`.
+    #[napi(js_name = "caseSensitive")]
+    pub case_sensitive: Option<bool>,
+
+    /// When `true`, disables diacritic folding in [`normalize`] so e.g.
+    /// "café" no longer matches "cafe". Defaults to `false`.
+    #[napi(js_name = "accentSensitive")]
+    pub accent_sensitive: Option<bool>,
 }
 
-#[napi(js_name = "computeScore")]
-pub fn compute_score(
-    params: ScoreParams,
-    note: NoteInput,
-) -> f64 {
-    const NOTE_ID_EXACT_MATCH: f64 = 1000.0;
-    const TITLE_EXACT_MATCH: f64 = 2000.0;
-    const TITLE_PREFIX_MATCH: f64 = 500.0;
-    const TITLE_WORD_MATCH: f64 = 300.0;
+/// Corpus-wide statistics used to weight `ScoreParams.tokens` by how
+/// discriminating each one is (BM25), rather than treating all tokens the
+/// same regardless of how common they are across the note set.
+#[napi(object)]
+pub struct CorpusStats {
+    /// Document frequency per token, aligned index-wise with `tokens`.
+    #[napi(js_name = "df")]
+    pub df: Vec<u32>,
+
+    #[napi(js_name = "noteCount")]
+    pub note_count: u32,
+
+    #[napi(js_name = "avgTitleLength")]
+    pub avg_title_length: f64,
+
+    #[napi(js_name = "avgPathLength")]
+    pub avg_path_length: f64,
+
+    #[napi(js_name = "k1")]
+    pub k1: Option<f64>,
+
+    #[napi(js_name = "b")]
+    pub b: Option<f64>,
+}
+
+/// A UTF-16 code-unit offset range into the original (un-normalized) title/path
+/// string, matching what `String.prototype.slice`/`substring` index by on the
+/// JS side. Computed internally in Unicode scalar (char) offsets and
+/// converted via [`to_utf16_ranges`] right before crossing the napi boundary.
+#[napi(object)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+#[napi(object)]
+pub struct ScoreResult {
+    pub score: f64,
+
+    #[napi(js_name = "titleRanges")]
+    pub title_ranges: Vec<MatchRange>,
+
+    #[napi(js_name = "pathRanges")]
+    pub path_ranges: Vec<MatchRange>,
+}
+
+const NOTE_ID_EXACT_MATCH: f64 = 1000.0;
+const TITLE_EXACT_MATCH: f64 = 2000.0;
+const TITLE_PREFIX_MATCH: f64 = 500.0;
+const TITLE_SUFFIX_MATCH: f64 = 500.0;
+const TITLE_WORD_MATCH: f64 = 300.0;
+
+const TOKEN_EXACT_MATCH: f64 = 4.0;
+const TOKEN_PREFIX_MATCH: f64 = 2.0;
+const TOKEN_CONTAINS_MATCH: f64 = 1.0;
+const TOKEN_FUZZY_MATCH: f64 = 0.5;
+
+const TITLE_FACTOR: f64 = 2.0;
+const PATH_FACTOR: f64 = 0.3;
+
+const HIDDEN_NOTE_PENALTY: f64 = 3.0;
+
+const MAX_FUZZY_SCORE_PER_TOKEN: f64 = 3.0;
+const MAX_FUZZY_TOKEN_LENGTH_MULTIPLIER: usize = 3;
+const MAX_TOTAL_FUZZY_SCORE: f64 = 200.0;
 
-    const TOKEN_EXACT_MATCH: f64 = 4.0;
-    const TOKEN_PREFIX_MATCH: f64 = 2.0;
-    const TOKEN_CONTAINS_MATCH: f64 = 1.0;
-    const TOKEN_FUZZY_MATCH: f64 = 0.5;
+/// Sentinel returned by an inverse atom match; any score this low or lower
+/// tells the caller to drop the note from the result set entirely.
+const EXCLUDED_NOTE_SCORE: f64 = -1_000_000.0;
 
-    const TITLE_FACTOR: f64 = 2.0;
-    const PATH_FACTOR: f64 = 0.3;
+const BM25_DEFAULT_K1: f64 = 1.2;
+const BM25_DEFAULT_B: f64 = 0.75;
 
-    const HIDDEN_NOTE_PENALTY: f64 = 3.0;
+/// Subtracted from a token match's contribution when it only matches a
+/// chunk case-insensitively, so an exact-case hit still outranks a
+/// case-folded one without rejecting the case-folded match outright.
+const CASE_MISMATCH_PENALTY: f64 = 0.5;
 
-    const MAX_FUZZY_SCORE_PER_TOKEN: f64 = 3.0;
-    const MAX_FUZZY_TOKEN_LENGTH_MULTIPLIER: usize = 3;
-    const MAX_TOTAL_FUZZY_SCORE: f64 = 200.0;
+/// Parses a raw query into typed atoms, fzf/skim-style:
+/// - leading `!` marks the atom inverse (excluded if it matches)
+/// - leading `^` anchors it to the start of the title
+/// - trailing `$` anchors it to the end of the title
+/// - `^foo$` requires the whole normalized title to equal `foo`
+/// - leading `'` forces an exact-substring match with fuzzy disabled
+#[napi(js_name = "parseQueryAtoms")]
+pub fn parse_query_atoms(query: String) -> Vec<QueryAtom> {
+    query
+        .split(' ')
+        .filter(|raw| !raw.is_empty())
+        .map(parse_query_atom)
+        .collect()
+}
+
+fn parse_query_atom(raw: &str) -> QueryAtom {
+    let mut text = raw;
+
+    let inverse = text.starts_with('!');
+    if inverse {
+        text = &text[1..];
+    }
+
+    let prefix_anchored = text.starts_with('^');
+    if prefix_anchored {
+        text = &text[1..];
+    }
 
+    let suffix_anchored = text.len() > 1 && text.ends_with('$');
+    if suffix_anchored {
+        text = &text[..text.len() - 1];
+    }
+
+    let exact_substring = !prefix_anchored && !suffix_anchored && text.starts_with('\'');
+    if exact_substring {
+        text = &text[1..];
+    }
+
+    let kind = match (prefix_anchored, suffix_anchored, exact_substring) {
+        (true, true, _) => QueryAtomKind::ExactAnchor,
+        (true, false, _) => QueryAtomKind::PrefixAnchor,
+        (false, true, _) => QueryAtomKind::SuffixAnchor,
+        (false, false, true) => QueryAtomKind::ExactSubstring,
+        (false, false, false) => QueryAtomKind::Plain,
+    };
+
+    QueryAtom {
+        kind,
+        inverse,
+        text: text.to_string(),
+    }
+}
+
+#[napi(js_name = "computeScore")]
+pub fn compute_score(
+    params: ScoreParams,
+    note: NoteInput,
+) -> ScoreResult {
     let mut score = 0.0;
     let mut fuzzy_score = 0.0;
+    let mut title_ranges: Vec<MatchRange> = Vec::new();
+    let mut path_ranges: Vec<MatchRange> = Vec::new();
 
     // ---- NOTE ID ----
     if note.id.to_lowercase() == params.query {
         score += NOTE_ID_EXACT_MATCH;
     }
 
+    let case_sensitive = params.case_sensitive.unwrap_or(false);
+    let accent_sensitive = params.accent_sensitive.unwrap_or(false);
+
     // ---- TITLE ----
-    let normalized_title = normalize(&note.title);
-
-    if normalized_title == params.normalized_query {
-        score += TITLE_EXACT_MATCH;
-    } else if normalized_title.starts_with(&params.normalized_query) {
-        score += TITLE_PREFIX_MATCH;
-    } else if word_match(&normalized_title, &params.normalized_query) {
-        score += TITLE_WORD_MATCH;
-    } else {
-        let f = fuzzy_title_score(
-            &normalized_title,
-            &params.normalized_query,
-            &mut fuzzy_score,
-            MAX_TOTAL_FUZZY_SCORE,
-        );
-        score += f;
+    let (normalized_title, title_offsets) = normalize_with_offsets(&note.title, accent_sensitive);
+
+    let idf_by_token = compute_idf_by_token(&params.tokens, params.corpus_stats.as_ref());
+    let title_bm25 = params.corpus_stats.as_ref().map(|stats| Bm25Context {
+        idf_by_token: &idf_by_token,
+        avg_field_len: stats.avg_title_length,
+        k1: stats.k1.unwrap_or(BM25_DEFAULT_K1),
+        b: stats.b.unwrap_or(BM25_DEFAULT_B),
+    });
+    let path_bm25 = params.corpus_stats.as_ref().map(|stats| Bm25Context {
+        idf_by_token: &idf_by_token,
+        avg_field_len: stats.avg_path_length,
+        k1: stats.k1.unwrap_or(BM25_DEFAULT_K1),
+        b: stats.b.unwrap_or(BM25_DEFAULT_B),
+    });
+
+    match params.atoms.as_ref().filter(|atoms| !atoms.is_empty()) {
+        Some(atoms) => {
+            for atom in atoms {
+                let atom_score = score_atom(
+                    atom,
+                    &normalized_title,
+                    &title_offsets,
+                    &note.title,
+                    &note.path_title,
+                    &mut fuzzy_score,
+                    &mut title_ranges,
+                    &mut path_ranges,
+                    case_sensitive,
+                    accent_sensitive,
+                    &params.tokens,
+                    &idf_by_token,
+                    params.corpus_stats.as_ref(),
+                );
+
+                if atom_score <= EXCLUDED_NOTE_SCORE {
+                    return ScoreResult {
+                        score: EXCLUDED_NOTE_SCORE,
+                        title_ranges: Vec::new(),
+                        path_ranges: Vec::new(),
+                    };
+                }
+
+                score += atom_score;
+            }
+        }
+        None => {
+            if normalized_title == params.normalized_query {
+                score += TITLE_EXACT_MATCH;
+                push_mapped_range(&mut title_ranges, &title_offsets, 0, title_offsets.len());
+            } else if normalized_title.starts_with(&params.normalized_query) {
+                score += TITLE_PREFIX_MATCH;
+                push_mapped_range(
+                    &mut title_ranges,
+                    &title_offsets,
+                    0,
+                    params.normalized_query.chars().count(),
+                );
+            } else if word_match(&normalized_title, &params.normalized_query) {
+                score += TITLE_WORD_MATCH;
+            } else {
+                let f = fuzzy_title_score(
+                    &normalized_title,
+                    &params.normalized_query,
+                    &mut fuzzy_score,
+                    MAX_TOTAL_FUZZY_SCORE,
+                );
+                score += f;
+            }
+
+            score += token_score(
+                &params.tokens,
+                &note.title,
+                TITLE_FACTOR,
+                &mut fuzzy_score,
+                MAX_TOTAL_FUZZY_SCORE,
+                MAX_FUZZY_SCORE_PER_TOKEN,
+                MAX_FUZZY_TOKEN_LENGTH_MULTIPLIER,
+                true,
+                title_bm25.as_ref(),
+                case_sensitive,
+                accent_sensitive,
+                &mut title_ranges,
+            );
+
+            score += token_score(
+                &params.tokens,
+                &note.path_title,
+                PATH_FACTOR,
+                &mut fuzzy_score,
+                MAX_TOTAL_FUZZY_SCORE,
+                MAX_FUZZY_SCORE_PER_TOKEN,
+                MAX_FUZZY_TOKEN_LENGTH_MULTIPLIER,
+                true,
+                path_bm25.as_ref(),
+                case_sensitive,
+                accent_sensitive,
+                &mut path_ranges,
+            );
+        }
     }
 
+    if note.hidden {
+        score /= HIDDEN_NOTE_PENALTY;
+    }
+
+    ScoreResult {
+        score,
+        title_ranges: to_utf16_ranges(merge_ranges(title_ranges), &note.title),
+        path_ranges: to_utf16_ranges(merge_ranges(path_ranges), &note.path_title),
+    }
+}
+
+/// Scores a single query atom against the title and the path, reusing the
+/// same factors as the plain-query path. Only `Plain`/`ExactSubstring` atoms
+/// can match on the path alone; anchored atoms must match the title.
+#[allow(clippy::too_many_arguments)]
+fn score_atom(
+    atom: &QueryAtom,
+    normalized_title: &str,
+    title_offsets: &[usize],
+    title: &str,
+    path_title: &str,
+    fuzzy_score: &mut f64,
+    title_ranges: &mut Vec<MatchRange>,
+    path_ranges: &mut Vec<MatchRange>,
+    case_sensitive: bool,
+    accent_sensitive: bool,
+    tokens: &[String],
+    idf_by_token: &[f64],
+    corpus_stats: Option<&CorpusStats>,
+) -> f64 {
+    let normalized_text = normalize(&atom.text, accent_sensitive);
+    if normalized_text.is_empty() {
+        return 0.0;
+    }
+
+    let anchor_range = match atom.kind {
+        QueryAtomKind::ExactAnchor => {
+            (normalized_title == normalized_text).then_some((0, title_offsets.len()))
+        }
+        QueryAtomKind::PrefixAnchor => normalized_title
+            .starts_with(&normalized_text)
+            .then(|| (0, normalized_text.chars().count())),
+        QueryAtomKind::SuffixAnchor => normalized_title.ends_with(&normalized_text).then(|| {
+            let total = title_offsets.len();
+            let len = normalized_text.chars().count();
+            (total - len, total)
+        }),
+        QueryAtomKind::Plain | QueryAtomKind::ExactSubstring => {
+            find_char_pos(normalized_title, &normalized_text)
+                .map(|start| (start, start + normalized_text.chars().count()))
+        }
+    };
+
+    let title_anchor_matched = anchor_range.is_some();
+
+    // `Plain`/`ExactSubstring` atoms are also checked against the path (see
+    // the `QueryAtomKind` docs); anchored atoms (`^`, `$`) are title-only.
+    let path_anchor_matched = matches!(atom.kind, QueryAtomKind::Plain | QueryAtomKind::ExactSubstring)
+        && find_char_pos(&normalize(path_title, accent_sensitive), &normalized_text).is_some();
+
+    let anchor_matched = title_anchor_matched || path_anchor_matched;
+
+    if atom.inverse {
+        return if anchor_matched { EXCLUDED_NOTE_SCORE } else { 0.0 };
+    }
+
+    if !anchor_matched {
+        return 0.0;
+    }
+
+    let mut score = match atom.kind {
+        QueryAtomKind::ExactAnchor => TITLE_EXACT_MATCH,
+        QueryAtomKind::PrefixAnchor => TITLE_PREFIX_MATCH,
+        QueryAtomKind::SuffixAnchor => TITLE_SUFFIX_MATCH,
+        QueryAtomKind::Plain | QueryAtomKind::ExactSubstring => 0.0,
+    };
+
+    if !matches!(atom.kind, QueryAtomKind::Plain | QueryAtomKind::ExactSubstring) {
+        if let Some((start, end)) = anchor_range {
+            push_mapped_range(title_ranges, title_offsets, start, end);
+        }
+    }
+
+    let allow_fuzzy = atom.kind != QueryAtomKind::ExactSubstring;
+    let single_token = [atom.text.clone()];
+
+    // Reuse the corpus idf computed for `tokens` when this atom's text
+    // happens to be one of them, so the structured query syntax doesn't
+    // silently lose BM25 weighting just because it's going through atoms
+    // instead of the plain-query path.
+    let atom_idf = tokens
+        .iter()
+        .position(|t| t == &atom.text)
+        .and_then(|idx| idf_by_token.get(idx..idx + 1));
+
+    let title_bm25 = corpus_stats.zip(atom_idf).map(|(stats, idf)| Bm25Context {
+        idf_by_token: idf,
+        avg_field_len: stats.avg_title_length,
+        k1: stats.k1.unwrap_or(BM25_DEFAULT_K1),
+        b: stats.b.unwrap_or(BM25_DEFAULT_B),
+    });
+    let path_bm25 = corpus_stats.zip(atom_idf).map(|(stats, idf)| Bm25Context {
+        idf_by_token: idf,
+        avg_field_len: stats.avg_path_length,
+        k1: stats.k1.unwrap_or(BM25_DEFAULT_K1),
+        b: stats.b.unwrap_or(BM25_DEFAULT_B),
+    });
+
     score += token_score(
-        &params.tokens,
-        &note.title,
+        &single_token,
+        title,
         TITLE_FACTOR,
-        &mut fuzzy_score,
+        fuzzy_score,
         MAX_TOTAL_FUZZY_SCORE,
         MAX_FUZZY_SCORE_PER_TOKEN,
         MAX_FUZZY_TOKEN_LENGTH_MULTIPLIER,
+        allow_fuzzy,
+        title_bm25.as_ref(),
+        case_sensitive,
+        accent_sensitive,
+        title_ranges,
     );
 
     score += token_score(
-        &params.tokens,
-        &note.path_title,
+        &single_token,
+        path_title,
         PATH_FACTOR,
-        &mut fuzzy_score,
+        fuzzy_score,
         MAX_TOTAL_FUZZY_SCORE,
         MAX_FUZZY_SCORE_PER_TOKEN,
         MAX_FUZZY_TOKEN_LENGTH_MULTIPLIER,
+        allow_fuzzy,
+        path_bm25.as_ref(),
+        case_sensitive,
+        accent_sensitive,
+        path_ranges,
     );
 
-    if note.hidden {
-        score /= HIDDEN_NOTE_PENALTY;
+    score
+}
+
+/// Per-token corpus weighting: precomputed idf plus the field-specific BM25
+/// length normalization inputs (`k1`, `b`, average field length).
+struct Bm25Context<'a> {
+    idf_by_token: &'a [f64],
+    avg_field_len: f64,
+    k1: f64,
+    b: f64,
+}
+
+/// Standard BM25 idf: rare tokens (low `df`) score higher than common ones.
+fn idf(note_count: u32, df: u32) -> f64 {
+    let n = note_count as f64;
+    let df = df as f64;
+    ((n - df + 0.5) / (df + 0.5) + 1.0).ln()
+}
+
+fn compute_idf_by_token(tokens: &[String], stats: Option<&CorpusStats>) -> Vec<f64> {
+    match stats {
+        Some(stats) => tokens
+            .iter()
+            .enumerate()
+            .map(|(i, _)| idf(stats.note_count, stats.df.get(i).copied().unwrap_or(0)))
+            .collect(),
+        None => Vec::new(),
     }
+}
 
-    score
+/// Saturating BM25 term-frequency weight: more occurrences help, but with
+/// diminishing returns, and matches in a longer-than-average field count
+/// for less.
+fn bm25_weight(tf: u32, field_len: f64, avg_len: f64, k1: f64, b: f64) -> f64 {
+    if tf == 0 {
+        return 0.0;
+    }
+
+    let tf = tf as f64;
+    tf * (k1 + 1.0) / (tf + k1 * (1.0 - b + b * field_len / avg_len.max(1.0)))
+}
+
+fn normalize(s: &str, accent_sensitive: bool) -> String {
+    normalize_with_offsets(s, accent_sensitive).0
+}
+
+/// Like [`normalize`], but also returns, for each char of the normalized
+/// output, the char index it came from in `s` — used to map a match found
+/// in normalized text back to a highlightable range in the original text.
+fn normalize_with_offsets(s: &str, accent_sensitive: bool) -> (String, Vec<usize>) {
+    let mut out = String::new();
+    let mut offsets = Vec::new();
+
+    for (i, ch) in s.chars().enumerate() {
+        if accent_sensitive {
+            if ch.is_alphanumeric() || ch == ' ' {
+                for lower in ch.to_lowercase() {
+                    out.push(lower);
+                    offsets.push(i);
+                }
+            }
+            continue;
+        }
+
+        decompose_canonical(ch, |decomposed| {
+            if is_combining_mark(decomposed) {
+                return;
+            }
+
+            if decomposed.is_alphanumeric() || decomposed == ' ' {
+                for lower in decomposed.to_lowercase() {
+                    out.push(lower);
+                    offsets.push(i);
+                }
+            }
+        });
+    }
+
+    (out, offsets)
+}
+
+/// Folds a single char to lowercase, dropping its diacritic (if any) unless
+/// `accent_sensitive` is set. Unlike [`normalize`], this always maps one
+/// input char to exactly one output char, so callers can use it to build a
+/// comparison array that stays index-aligned with the original text.
+fn fold_char(c: char, accent_sensitive: bool) -> char {
+    if accent_sensitive {
+        return c.to_lowercase().next().unwrap_or(c);
+    }
+
+    let mut base = None;
+    decompose_canonical(c, |decomposed| {
+        if base.is_none() && !is_combining_mark(decomposed) {
+            base = Some(decomposed);
+        }
+    });
+
+    base.unwrap_or(c).to_lowercase().next().unwrap_or(c)
+}
+
+/// Maps a `[start, end)` char range of a normalized string back to the
+/// original string's char offsets, using the offsets from
+/// [`normalize_with_offsets`].
+fn map_range(offsets: &[usize], start: usize, end: usize) -> Option<MatchRange> {
+    if end == 0 || start >= end || end > offsets.len() {
+        return None;
+    }
+
+    Some(MatchRange {
+        start: offsets[start] as u32,
+        end: (offsets[end - 1] + 1) as u32,
+    })
+}
+
+fn push_mapped_range(ranges: &mut Vec<MatchRange>, offsets: &[usize], start: usize, end: usize) {
+    if let Some(range) = map_range(offsets, start, end) {
+        ranges.push(range);
+    }
+}
+
+/// Finds the char (not byte) index at which `needle` first occurs in `haystack`.
+fn find_char_pos(haystack: &str, needle: &str) -> Option<usize> {
+    let byte_pos = haystack.find(needle)?;
+    Some(haystack[..byte_pos].chars().count())
+}
+
+/// Sorts and collapses overlapping/adjacent ranges so the UI doesn't have to.
+fn merge_ranges(mut ranges: Vec<MatchRange>) -> Vec<MatchRange> {
+    ranges.sort_by_key(|r| (r.start, r.end));
+
+    let mut merged: Vec<MatchRange> = Vec::new();
+
+    for range in ranges {
+        if let Some(last) = merged.last_mut() {
+            if range.start <= last.end {
+                last.end = last.end.max(range.end);
+                continue;
+            }
+        }
+
+        merged.push(range);
+    }
+
+    merged
+}
+
+/// Cumulative UTF-16 code-unit offset at each char boundary of `s`: entry
+/// `i` is the number of UTF-16 units before the `i`-th char, so the table
+/// has `s.chars().count() + 1` entries. Chars outside the Basic Multilingual
+/// Plane (most emoji, some CJK extensions) are 2 UTF-16 units but 1 Rust
+/// `char`, so this table is required to convert a char-offset range into a
+/// UTF-16 offset range that a JS `String.prototype.slice` can use directly.
+fn utf16_offsets(s: &str) -> Vec<usize> {
+    let mut offsets = Vec::with_capacity(s.chars().count() + 1);
+    offsets.push(0);
+
+    let mut acc = 0;
+    for ch in s.chars() {
+        acc += ch.len_utf16();
+        offsets.push(acc);
+    }
+
+    offsets
 }
 
-fn normalize(s: &str) -> String {
-    s.to_lowercase()
-        .replace(|c: char| !c.is_alphanumeric() && c != ' ', "")
+/// Converts char-offset `MatchRange`s (as produced internally throughout
+/// this module) into UTF-16 code-unit offsets against the original `text`
+/// they were found in, for crossing the napi boundary.
+fn to_utf16_ranges(ranges: Vec<MatchRange>, text: &str) -> Vec<MatchRange> {
+    let table = utf16_offsets(text);
+
+    ranges
+        .into_iter()
+        .map(|r| MatchRange {
+            start: table[r.start as usize] as u32,
+            end: table[r.end as usize] as u32,
+        })
+        .collect()
 }
 
 fn word_match(text: &str, query: &str) -> bool {
@@ -168,6 +709,137 @@ fn fuzzy_title_score(
     }
 }
 
+/// A whitespace-delimited word from the original text, with its char offsets.
+struct Word {
+    start: usize,
+    text: String,
+}
+
+fn char_words(text: &str) -> Vec<Word> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut words = Vec::new();
+    let mut start: Option<usize> = None;
+
+    for (i, ch) in chars.iter().enumerate() {
+        if ch.is_whitespace() {
+            if let Some(s) = start.take() {
+                words.push(Word {
+                    start: s,
+                    text: chars[s..i].iter().collect(),
+                });
+            }
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+
+    if let Some(s) = start {
+        words.push(Word {
+            start: s,
+            text: chars[s..].iter().collect(),
+        });
+    }
+
+    words
+}
+
+/// Match score per aligned char; boundary/consecutive bonuses below are
+/// expressed relative to this.
+const ALIGN_MATCH_SCORE: f64 = 16.0;
+/// Cost of skipping to a new, non-consecutive chunk position.
+const ALIGN_GAP_PENALTY: f64 = 3.0;
+/// Additional cost per extra chunk char skipped beyond the first.
+const ALIGN_GAP_EXTENSION_PENALTY: f64 = 1.0;
+/// Bonus for matching right after a delimiter or at a camelCase boundary.
+const ALIGN_BOUNDARY_BONUS: f64 = 10.0;
+/// Bonus for matching at the very start of the chunk.
+const ALIGN_START_BONUS: f64 = 6.0;
+/// Extra bonus for matching immediately after the previous matched char.
+const ALIGN_CONSECUTIVE_BONUS: f64 = 8.0;
+
+/// Tokens at least this long use trigram similarity instead of
+/// `edit_distance` for their fuzzy fallback — it tolerates transpositions
+/// and reorderings that a max-distance-3 edit distance simply rejects.
+const TRIGRAM_FUZZY_MIN_LEN: usize = 4;
+const TRIGRAM_SIMILARITY_THRESHOLD: f64 = 0.4;
+
+/// Sentinel boundary marker used to pad trigrams so the first/last real
+/// chars still participate in a full 3-gram.
+const TRIGRAM_PAD: char = '\u{1}';
+
+type TrigramSet = HashMap<(char, char, char), u32>;
+
+/// Builds the multiset of padded 3-grams of `chars`, used for Dice-coefficient
+/// similarity.
+fn trigrams(chars: &[char]) -> TrigramSet {
+    let mut padded = Vec::with_capacity(chars.len() + 2);
+    padded.push(TRIGRAM_PAD);
+    padded.extend_from_slice(chars);
+    padded.push(TRIGRAM_PAD);
+
+    let mut grams = TrigramSet::new();
+    if padded.len() >= 3 {
+        for w in padded.windows(3) {
+            *grams.entry((w[0], w[1], w[2])).or_insert(0) += 1;
+        }
+    }
+
+    grams
+}
+
+/// Dice coefficient `2*|A∩B| / (|A|+|B|)` over two trigram multisets.
+fn trigram_similarity(a: &TrigramSet, b: &TrigramSet) -> f64 {
+    let a_count: u32 = a.values().sum();
+    let b_count: u32 = b.values().sum();
+
+    if a_count == 0 || b_count == 0 {
+        return 0.0;
+    }
+
+    let intersection: u32 = a
+        .iter()
+        .filter_map(|(gram, &count)| b.get(gram).map(|&other| count.min(other)))
+        .sum();
+
+    2.0 * intersection as f64 / (a_count + b_count) as f64
+}
+
+/// A word from the field text, pre-normalized once so every token can be
+/// checked against it cheaply.
+struct PreparedWord {
+    start: usize,
+    raw_chars: Vec<char>,
+    cmp_chars: Vec<char>,
+    norm_chunk: String,
+    chunk_offsets: Vec<usize>,
+    trigrams: TrigramSet,
+}
+
+fn prepare_words(text: &str, accent_sensitive: bool) -> Vec<PreparedWord> {
+    char_words(text)
+        .into_iter()
+        .map(|word| {
+            let raw_chars: Vec<char> = word.text.chars().collect();
+            let cmp_chars: Vec<char> = raw_chars
+                .iter()
+                .map(|&c| fold_char(c, accent_sensitive))
+                .collect();
+            let (norm_chunk, chunk_offsets) = normalize_with_offsets(&word.text, accent_sensitive);
+            let trigrams = trigrams(&cmp_chars);
+
+            PreparedWord {
+                start: word.start,
+                raw_chars,
+                cmp_chars,
+                norm_chunk,
+                chunk_offsets,
+                trigrams,
+            }
+        })
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
 fn token_score(
     tokens: &[String],
     text: &str,
@@ -176,42 +848,775 @@ fn token_score(
     total_cap: f64,
     per_token_cap: f64,
     token_len_cap: usize,
+    allow_fuzzy: bool,
+    bm25: Option<&Bm25Context>,
+    case_sensitive: bool,
+    accent_sensitive: bool,
+    ranges: &mut Vec<MatchRange>,
 ) -> f64 {
-    let norm = normalize(text);
-    let chunks: Vec<&str> = norm.split(' ').collect();
+    let words = prepare_words(text, accent_sensitive);
+    let field_len = words.len() as f64;
 
     let mut score = 0.0;
 
-    for chunk in &chunks {
-        for token in tokens {
-            let norm_token = normalize(token);
+    for (token_idx, token) in tokens.iter().enumerate() {
+        let norm_token = normalize(token, accent_sensitive);
+        if norm_token.is_empty() {
+            continue;
+        }
+
+        // `tf`/`best_raw` are accumulated from the same matchers used below
+        // (DP alignment, trigram/edit-distance fallback, or the exact/
+        // prefix/contains tiers) so idf weighting actually lines up with
+        // what scored. BM25's tf term already saturates for repeat
+        // occurrences, so it's applied once to the field's single best
+        // match rather than multiplied into every matching word — doing
+        // the latter would reintroduce linear scaling with occurrence
+        // count on top of the saturating tf term.
+        let mut tf: u32 = 0;
+        let mut best_raw = 0.0_f64;
+        let mut best_is_fuzzy = false;
 
-            if chunk == &norm_token {
-                score += 4.0 * token.len() as f64 * factor;
-            } else if chunk.starts_with(&norm_token) {
-                score += 2.0 * token.len() as f64 * factor;
-            } else if chunk.contains(&norm_token) {
-                score += 1.0 * token.len() as f64 * factor;
-            } else {
-                if *fuzzy_score >= total_cap || norm_token.len() < 3 {
+        if allow_fuzzy {
+            let token_chars: Vec<char> = norm_token.chars().collect();
+            let token_trigrams = (norm_token.len() >= TRIGRAM_FUZZY_MIN_LEN).then(|| trigrams(&token_chars));
+
+            for word in &words {
+                if let Some((align_score, positions)) =
+                    align_token(&token_chars, &word.cmp_chars, &word.raw_chars)
+                {
+                    if let Some(penalty) =
+                        case_match_penalty(token, &word.raw_chars, &positions, case_sensitive)
+                    {
+                        tf += 1;
+                        let raw = (align_score * factor - penalty).max(0.0);
+                        if raw > best_raw {
+                            best_raw = raw;
+                            best_is_fuzzy = false;
+                        }
+                        push_positions_range(ranges, word.start, &positions);
+                    }
+                } else if *fuzzy_score >= total_cap {
                     continue;
-                }
+                } else if let Some(token_trigrams) = &token_trigrams {
+                    let similarity = trigram_similarity(token_trigrams, &word.trigrams);
+
+                    if similarity > TRIGRAM_SIMILARITY_THRESHOLD {
+                        tf += 1;
+                        let weight = TOKEN_FUZZY_MATCH * similarity;
+                        let capped_len = token.len().min(token_len_cap);
+                        let raw = (weight * capped_len as f64 * factor).min(per_token_cap);
+                        if raw > best_raw {
+                            best_raw = raw;
+                            best_is_fuzzy = true;
+                        }
+                    }
+                } else if norm_token.len() == 3 {
+                    let norm_chunk: String = word.cmp_chars.iter().collect();
+                    let dist = edit_distance(&norm_chunk, &norm_token, 3);
 
-                let dist = edit_distance(chunk, &norm_token, 3);
+                    if dist <= 3 {
+                        tf += 1;
+                        let weight = TOKEN_FUZZY_MATCH * (1.0 - dist as f64 / 3.0);
+                        let capped_len = token.len().min(token_len_cap);
+                        let raw = (weight * capped_len as f64 * factor).min(per_token_cap);
+                        if raw > best_raw {
+                            best_raw = raw;
+                            best_is_fuzzy = true;
+                        }
+                    }
+                }
+            }
+        } else {
+            for word in &words {
+                let (base, norm_start, norm_end) = if word.norm_chunk == norm_token {
+                    (TOKEN_EXACT_MATCH, 0, word.norm_chunk.chars().count())
+                } else if word.norm_chunk.starts_with(&norm_token) {
+                    (TOKEN_PREFIX_MATCH, 0, norm_token.chars().count())
+                } else if let Some(pos) = find_char_pos(&word.norm_chunk, &norm_token) {
+                    (TOKEN_CONTAINS_MATCH, pos, pos + norm_token.chars().count())
+                } else {
+                    continue;
+                };
 
-                if dist <= 3 {
-                    let weight = 0.5 * (1.0 - dist as f64 / 3.0);
-                    let capped_len = token.len().min(token_len_cap);
-                    let fuzzy = (weight * capped_len as f64 * factor)
-                        .min(per_token_cap);
+                let positions: Vec<usize> = (norm_start..norm_end)
+                    .map(|p| word.chunk_offsets[p])
+                    .collect();
 
-                    score += fuzzy;
-                    *fuzzy_score += fuzzy;
+                if let Some(penalty) =
+                    case_match_penalty(token, &word.raw_chars, &positions, case_sensitive)
+                {
+                    tf += 1;
+                    let raw = (base * token.len() as f64 * factor - penalty).max(0.0);
+                    best_raw = best_raw.max(raw);
+                    push_word_range(ranges, word.start, &word.chunk_offsets, norm_start, norm_end);
                 }
             }
         }
+
+        let bm25_factor = bm25
+            .map(|ctx| {
+                if tf == 0 {
+                    return 1.0;
+                }
+
+                let idf = ctx.idf_by_token.get(token_idx).copied().unwrap_or(1.0);
+                idf * bm25_weight(tf, field_len, ctx.avg_field_len, ctx.k1, ctx.b)
+            })
+            .unwrap_or(1.0);
+
+        let weighted = best_raw * bm25_factor;
+        score += weighted;
+        if best_is_fuzzy {
+            *fuzzy_score += weighted;
+        }
     }
 
     score
 }
 
+/// Compares a matched token against the original (case-preserving) chars at
+/// its matched `positions` in the field text. Returns `Some(0.0)` on an
+/// exact-case match, `Some(CASE_MISMATCH_PENALTY)` on a case-folded-only
+/// match, or `None` to reject the match entirely when `case_sensitive` is
+/// set. Token/position length mismatches (e.g. an accent-folded token is
+/// shorter than its un-folded original) are treated as a non-issue, since
+/// there's no meaningful per-char casing to compare.
+fn case_match_penalty(
+    token: &str,
+    raw_chars: &[char],
+    positions: &[usize],
+    case_sensitive: bool,
+) -> Option<f64> {
+    let token_chars: Vec<char> = token.chars().collect();
+    if token_chars.len() != positions.len() {
+        return Some(0.0);
+    }
+
+    let exact_case = positions
+        .iter()
+        .zip(token_chars.iter())
+        .all(|(&pos, &tc)| raw_chars.get(pos) == Some(&tc));
+
+    if exact_case {
+        Some(0.0)
+    } else if case_sensitive {
+        None
+    } else {
+        Some(CASE_MISMATCH_PENALTY)
+    }
+}
+
+/// Boundary class a matched chunk position falls into: highest right after
+/// a delimiter or at a camelCase hump, medium at the very start, zero
+/// elsewhere. `chars` is the chunk's *original*, case-preserving text, since
+/// normalization erases both the delimiters and the case this depends on.
+fn boundary_bonus(chars: &[char], pos: usize) -> f64 {
+    if pos == 0 {
+        return ALIGN_START_BONUS;
+    }
+
+    let prev = chars[pos - 1];
+    let cur = chars[pos];
+
+    if !prev.is_alphanumeric() || (prev.is_lowercase() && cur.is_uppercase()) {
+        ALIGN_BOUNDARY_BONUS
+    } else {
+        0.0
+    }
+}
+
+/// Smith-Waterman-like alignment of `token_chars` as an ordered (possibly
+/// non-contiguous) subsequence of `cmp_chars`, rewarding matches that land
+/// on a boundary (see [`boundary_bonus`]) and runs of consecutive matches.
+/// `raw_chars` is `cmp_chars` before case-folding, used only for boundary
+/// classification. Returns the best alignment score and the matched char
+/// positions in the chunk (for highlighting), or `None` if `token_chars`
+/// doesn't occur as a subsequence of `cmp_chars` at all.
+fn align_token(
+    token_chars: &[char],
+    cmp_chars: &[char],
+    raw_chars: &[char],
+) -> Option<(f64, Vec<usize>)> {
+    let m = token_chars.len();
+    let n = cmp_chars.len();
+
+    if m == 0 || n == 0 {
+        return None;
+    }
+
+    // match_score[i][j]: best score aligning token[..i] to the chunk with
+    // token[i - 1] matched exactly at chunk position j - 1.
+    let mut match_score = vec![vec![f64::NEG_INFINITY; n + 1]; m + 1];
+    // pred[i][j]: 1-based chunk position of the previously matched char in
+    // this alignment (0 means token[i - 1] is the first matched char).
+    let mut pred = vec![vec![0usize; n + 1]; m + 1];
+    // best_score[i][j] / best_pos[i][j]: running max of match_score[i][..=j]
+    // and the chunk position (1-based) that achieved it.
+    let mut best_score = vec![vec![0.0; n + 1]; m + 1];
+    let mut best_pos = vec![vec![0usize; n + 1]; m + 1];
+
+    for i in 1..=m {
+        best_score[i][0] = f64::NEG_INFINITY;
+
+        // Decayed running max of best_score[i - 1][..j - 1], so each row is
+        // O(n) instead of rescanning every earlier gap length.
+        let mut gap_best = f64::NEG_INFINITY;
+        let mut gap_pos = 0usize;
+
+        for j in 1..=n {
+            if j >= 2 {
+                gap_best -= ALIGN_GAP_EXTENSION_PENALTY;
+                let opened = best_score[i - 1][j - 2] - ALIGN_GAP_PENALTY;
+                if opened > gap_best {
+                    gap_best = opened;
+                    gap_pos = best_pos[i - 1][j - 2];
+                }
+            }
+
+            if token_chars[i - 1] == cmp_chars[j - 1] {
+                let consecutive = match_score[i - 1][j - 1];
+
+                let (carry, carry_pos) = if i == 1 {
+                    (0.0, 0)
+                } else if consecutive > f64::NEG_INFINITY
+                    && consecutive + ALIGN_CONSECUTIVE_BONUS >= gap_best
+                {
+                    (consecutive + ALIGN_CONSECUTIVE_BONUS, j - 1)
+                } else {
+                    (gap_best, gap_pos)
+                };
+
+                if i == 1 || carry > f64::NEG_INFINITY {
+                    match_score[i][j] = ALIGN_MATCH_SCORE + boundary_bonus(raw_chars, j - 1) + carry.max(0.0);
+                    pred[i][j] = carry_pos;
+                }
+            }
+
+            if match_score[i][j] > best_score[i][j - 1] {
+                best_score[i][j] = match_score[i][j];
+                best_pos[i][j] = j;
+            } else {
+                best_score[i][j] = best_score[i][j - 1];
+                best_pos[i][j] = best_pos[i][j - 1];
+            }
+        }
+    }
+
+    if best_score[m][n] <= f64::NEG_INFINITY {
+        return None;
+    }
+
+    let mut positions = Vec::with_capacity(m);
+    let mut j = best_pos[m][n];
+    for i in (1..=m).rev() {
+        positions.push(j - 1);
+        j = pred[i][j];
+    }
+    positions.reverse();
+
+    Some((best_score[m][n], positions))
+}
+
+fn push_word_range(
+    ranges: &mut Vec<MatchRange>,
+    word_start: usize,
+    chunk_offsets: &[usize],
+    norm_start: usize,
+    norm_end: usize,
+) {
+    if let Some(range) = map_range(chunk_offsets, norm_start, norm_end) {
+        ranges.push(MatchRange {
+            start: word_start as u32 + range.start,
+            end: word_start as u32 + range.end,
+        });
+    }
+}
+
+/// Coalesces a (possibly non-contiguous) sorted list of matched char
+/// positions within a word into highlightable ranges relative to the
+/// original text.
+fn push_positions_range(ranges: &mut Vec<MatchRange>, word_start: usize, positions: &[usize]) {
+    let mut iter = positions.iter().copied();
+
+    let Some(first) = iter.next() else {
+        return;
+    };
+
+    let mut start = first;
+    let mut end = first + 1;
+
+    for pos in iter {
+        if pos == end {
+            end = pos + 1;
+        } else {
+            ranges.push(MatchRange {
+                start: (word_start + start) as u32,
+                end: (word_start + end) as u32,
+            });
+            start = pos;
+            end = pos + 1;
+        }
+    }
+
+    ranges.push(MatchRange {
+        start: (word_start + start) as u32,
+        end: (word_start + end) as u32,
+    });
+}
+
+// -------- RANK FUSION --------
+
+const RRF_DEFAULT_K: f64 = 60.0;
+
+/// A note's position within one ranked list (1-based, smaller is better).
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct RankedEntry {
+    #[napi(js_name = "noteId")]
+    pub note_id: String,
+
+    #[napi(js_name = "rank")]
+    pub rank: u32,
+}
+
+/// One ranking to fuse, e.g. this crate's lexical score or an external
+/// vector-similarity search, with an optional weight in the fused blend.
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct RankedList {
+    #[napi(js_name = "entries")]
+    pub entries: Vec<RankedEntry>,
+
+    #[napi(js_name = "weight")]
+    pub weight: Option<f64>,
+}
+
+#[napi(object)]
+pub struct FusedEntry {
+    #[napi(js_name = "noteId")]
+    pub note_id: String,
+
+    #[napi(js_name = "score")]
+    pub score: f64,
+}
+
+/// Blends several ranked lists (e.g. this lexical ranking plus a
+/// vector-similarity ranking) via reciprocal rank fusion: each note's fused
+/// score is `Σ weight / (k + rank)` across the lists it appears in, with
+/// notes missing from a list contributing nothing from it. Because RRF only
+/// needs ranks, it sidesteps the mismatched score scales between heuristics
+/// like `computeScore` (hundreds/thousands) and cosine similarity (0-1).
+#[napi(js_name = "fuseRankings")]
+pub fn fuse_rankings(lists: Vec<RankedList>, k: Option<f64>) -> Vec<FusedEntry> {
+    let k = k.unwrap_or(RRF_DEFAULT_K);
+    let mut fused: HashMap<String, f64> = HashMap::new();
+
+    for list in &lists {
+        let weight = list.weight.unwrap_or(1.0);
+
+        for entry in &list.entries {
+            let contribution = weight / (k + entry.rank as f64);
+            *fused.entry(entry.note_id.clone()).or_insert(0.0) += contribution;
+        }
+    }
+
+    let mut results: Vec<FusedEntry> = fused
+        .into_iter()
+        .map(|(note_id, score)| FusedEntry { note_id, score })
+        .collect();
+
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_plain_atom() {
+        let atom = parse_query_atom("foo");
+        assert_eq!(atom.kind, QueryAtomKind::Plain);
+        assert!(!atom.inverse);
+        assert_eq!(atom.text, "foo");
+    }
+
+    #[test]
+    fn parses_inverse_atom() {
+        let atom = parse_query_atom("!foo");
+        assert_eq!(atom.kind, QueryAtomKind::Plain);
+        assert!(atom.inverse);
+        assert_eq!(atom.text, "foo");
+    }
+
+    #[test]
+    fn parses_prefix_anchor() {
+        let atom = parse_query_atom("^foo");
+        assert_eq!(atom.kind, QueryAtomKind::PrefixAnchor);
+        assert_eq!(atom.text, "foo");
+    }
+
+    #[test]
+    fn parses_suffix_anchor() {
+        let atom = parse_query_atom("foo$");
+        assert_eq!(atom.kind, QueryAtomKind::SuffixAnchor);
+        assert_eq!(atom.text, "foo");
+    }
+
+    #[test]
+    fn plain_atom_matches_against_path_when_title_has_no_hit() {
+        let atom = QueryAtom {
+            kind: QueryAtomKind::Plain,
+            inverse: false,
+            text: "secret".to_string(),
+        };
+        let (normalized_title, title_offsets) = normalize_with_offsets("My Document", false);
+        let mut fuzzy_score = 0.0;
+        let mut title_ranges = Vec::new();
+        let mut path_ranges = Vec::new();
+        let tokens: Vec<String> = Vec::new();
+        let idf_by_token: Vec<f64> = Vec::new();
+
+        let score = score_atom(
+            &atom,
+            &normalized_title,
+            &title_offsets,
+            "My Document",
+            "Projects > secret > My Document",
+            &mut fuzzy_score,
+            &mut title_ranges,
+            &mut path_ranges,
+            false,
+            false,
+            &tokens,
+            &idf_by_token,
+            None,
+        );
+
+        assert!(score > 0.0);
+    }
+
+    #[test]
+    fn inverse_atom_excludes_on_path_match_too() {
+        let atom = QueryAtom {
+            kind: QueryAtomKind::Plain,
+            inverse: true,
+            text: "secret".to_string(),
+        };
+        let (normalized_title, title_offsets) = normalize_with_offsets("My Document", false);
+        let mut fuzzy_score = 0.0;
+        let mut title_ranges = Vec::new();
+        let mut path_ranges = Vec::new();
+        let tokens: Vec<String> = Vec::new();
+        let idf_by_token: Vec<f64> = Vec::new();
+
+        let score = score_atom(
+            &atom,
+            &normalized_title,
+            &title_offsets,
+            "My Document",
+            "Projects > secret > My Document",
+            &mut fuzzy_score,
+            &mut title_ranges,
+            &mut path_ranges,
+            false,
+            false,
+            &tokens,
+            &idf_by_token,
+            None,
+        );
+
+        assert_eq!(score, EXCLUDED_NOTE_SCORE);
+    }
+
+    #[test]
+    fn parses_exact_anchor() {
+        let atom = parse_query_atom("^foo$");
+        assert_eq!(atom.kind, QueryAtomKind::ExactAnchor);
+        assert_eq!(atom.text, "foo");
+    }
+
+    #[test]
+    fn parses_exact_substring() {
+        let atom = parse_query_atom("'foo");
+        assert_eq!(atom.kind, QueryAtomKind::ExactSubstring);
+        assert_eq!(atom.text, "foo");
+    }
+
+    #[test]
+    fn splits_query_into_multiple_atoms() {
+        let atoms = parse_query_atoms("^foo !bar baz$".to_string());
+        assert_eq!(atoms.len(), 3);
+        assert_eq!(atoms[0].kind, QueryAtomKind::PrefixAnchor);
+        assert!(atoms[1].inverse);
+        assert_eq!(atoms[2].kind, QueryAtomKind::SuffixAnchor);
+    }
+
+    #[test]
+    fn utf16_offsets_account_for_astral_chars() {
+        // "🎉" is one Rust `char` but two UTF-16 code units.
+        let table = utf16_offsets("a🎉b");
+        assert_eq!(table, vec![0, 1, 3, 4]);
+    }
+
+    #[test]
+    fn to_utf16_ranges_converts_char_offsets_past_astral_chars() {
+        let text = "🎉bar";
+        // Char range [1, 4) covers "bar" starting right after the emoji.
+        let ranges = to_utf16_ranges(vec![MatchRange { start: 1, end: 4 }], text);
+        assert_eq!(ranges, vec![MatchRange { start: 2, end: 5 }]);
+    }
+
+    #[test]
+    fn merge_ranges_collapses_overlaps() {
+        let merged = merge_ranges(vec![
+            MatchRange { start: 0, end: 3 },
+            MatchRange { start: 2, end: 5 },
+            MatchRange { start: 10, end: 12 },
+        ]);
+
+        assert_eq!(
+            merged,
+            vec![MatchRange { start: 0, end: 5 }, MatchRange { start: 10, end: 12 }]
+        );
+    }
+
+    #[test]
+    fn align_token_prefers_boundary_match_over_midword_match() {
+        let token_chars: Vec<char> = "bar".chars().collect();
+
+        // "FooBar": "bar" lands right on a camelCase boundary.
+        let boundary_raw: Vec<char> = "FooBar".chars().collect();
+        let boundary_cmp: Vec<char> =
+            boundary_raw.iter().map(|c| c.to_ascii_lowercase()).collect();
+        let (boundary_score, _) =
+            align_token(&token_chars, &boundary_cmp, &boundary_raw).unwrap();
+
+        // "rebar": "bar" is a contiguous match but mid-word, no boundary.
+        let midword_raw: Vec<char> = "rebar".chars().collect();
+        let midword_cmp: Vec<char> =
+            midword_raw.iter().map(|c| c.to_ascii_lowercase()).collect();
+        let (midword_score, _) = align_token(&token_chars, &midword_cmp, &midword_raw).unwrap();
+
+        assert!(boundary_score > midword_score);
+    }
+
+    #[test]
+    fn idf_decreases_as_document_frequency_increases() {
+        let rare = idf(100, 1);
+        let common = idf(100, 50);
+        assert!(rare > common);
+    }
+
+    #[test]
+    fn bm25_weight_is_zero_for_zero_term_frequency() {
+        assert_eq!(bm25_weight(0, 5.0, 5.0, BM25_DEFAULT_K1, BM25_DEFAULT_B), 0.0);
+    }
+
+    #[test]
+    fn prefix_match_idf_weighting_distinguishes_rare_from_common_tokens() {
+        let tokens = vec!["note".to_string()];
+
+        let rare_idf = vec![idf(1000, 2)];
+        let common_idf = vec![idf(1000, 900)];
+
+        let rare_ctx = Bm25Context {
+            idf_by_token: &rare_idf,
+            avg_field_len: 1.0,
+            k1: BM25_DEFAULT_K1,
+            b: BM25_DEFAULT_B,
+        };
+        let common_ctx = Bm25Context {
+            idf_by_token: &common_idf,
+            avg_field_len: 1.0,
+            k1: BM25_DEFAULT_K1,
+            b: BM25_DEFAULT_B,
+        };
+
+        let mut rare_ranges = Vec::new();
+        let mut rare_fuzzy = 0.0;
+        let rare_score = token_score(
+            &tokens,
+            "notebook",
+            TITLE_FACTOR,
+            &mut rare_fuzzy,
+            MAX_TOTAL_FUZZY_SCORE,
+            MAX_FUZZY_SCORE_PER_TOKEN,
+            MAX_FUZZY_TOKEN_LENGTH_MULTIPLIER,
+            true,
+            Some(&rare_ctx),
+            false,
+            false,
+            &mut rare_ranges,
+        );
+
+        let mut common_ranges = Vec::new();
+        let mut common_fuzzy = 0.0;
+        let common_score = token_score(
+            &tokens,
+            "notebook",
+            TITLE_FACTOR,
+            &mut common_fuzzy,
+            MAX_TOTAL_FUZZY_SCORE,
+            MAX_FUZZY_SCORE_PER_TOKEN,
+            MAX_FUZZY_TOKEN_LENGTH_MULTIPLIER,
+            true,
+            Some(&common_ctx),
+            false,
+            false,
+            &mut common_ranges,
+        );
+
+        assert!(rare_score > common_score);
+    }
+
+    #[test]
+    fn repeated_occurrences_do_not_multiply_the_bm25_factor() {
+        let tokens = vec!["cat".to_string()];
+        let idf_by_token = vec![1.0];
+        let ctx = Bm25Context {
+            idf_by_token: &idf_by_token,
+            avg_field_len: 5.0,
+            k1: BM25_DEFAULT_K1,
+            b: BM25_DEFAULT_B,
+        };
+
+        let mut single_fuzzy = 0.0;
+        let mut single_ranges = Vec::new();
+        let single_score = token_score(
+            &tokens,
+            "cat",
+            TITLE_FACTOR,
+            &mut single_fuzzy,
+            MAX_TOTAL_FUZZY_SCORE,
+            MAX_FUZZY_SCORE_PER_TOKEN,
+            MAX_FUZZY_TOKEN_LENGTH_MULTIPLIER,
+            true,
+            Some(&ctx),
+            false,
+            false,
+            &mut single_ranges,
+        );
+
+        let mut repeated_fuzzy = 0.0;
+        let mut repeated_ranges = Vec::new();
+        let repeated_score = token_score(
+            &tokens,
+            "cat cat cat cat cat cat cat cat cat cat",
+            TITLE_FACTOR,
+            &mut repeated_fuzzy,
+            MAX_TOTAL_FUZZY_SCORE,
+            MAX_FUZZY_SCORE_PER_TOKEN,
+            MAX_FUZZY_TOKEN_LENGTH_MULTIPLIER,
+            true,
+            Some(&ctx),
+            false,
+            false,
+            &mut repeated_ranges,
+        );
+
+        // BM25's tf term saturates near k1 + 1; ten occurrences should not
+        // come anywhere close to a 10x (let alone the ~12x regression the
+        // reviewer measured) over a single occurrence.
+        assert!(repeated_score < single_score * (BM25_DEFAULT_K1 + 1.0));
+    }
+
+    #[test]
+    fn trigram_similarity_tolerates_transposition() {
+        let a: Vec<char> = "recieve".chars().collect();
+        let b: Vec<char> = "receive".chars().collect();
+
+        let similarity = trigram_similarity(&trigrams(&a), &trigrams(&b));
+        assert!(similarity > TRIGRAM_SIMILARITY_THRESHOLD);
+    }
+
+    #[test]
+    fn trigram_similarity_is_zero_for_unrelated_words() {
+        let a: Vec<char> = "receive".chars().collect();
+        let b: Vec<char> = "xylophone".chars().collect();
+
+        let similarity = trigram_similarity(&trigrams(&a), &trigrams(&b));
+        assert_eq!(similarity, 0.0);
+    }
+
+    #[test]
+    fn fuse_rankings_blends_and_orders_by_combined_rank() {
+        let lexical = RankedList {
+            entries: vec![
+                RankedEntry { note_id: "a".to_string(), rank: 1 },
+                RankedEntry { note_id: "b".to_string(), rank: 2 },
+            ],
+            weight: None,
+        };
+        let semantic = RankedList {
+            entries: vec![
+                RankedEntry { note_id: "b".to_string(), rank: 1 },
+                RankedEntry { note_id: "a".to_string(), rank: 3 },
+            ],
+            weight: None,
+        };
+
+        let fused = fuse_rankings(vec![lexical, semantic], None);
+
+        // "b" ranks first in one list and second in the other; "a" ranks
+        // first in one list but third in the other, so the note present
+        // near the top of both lists should come out ahead.
+        assert_eq!(fused[0].note_id, "b");
+        assert_eq!(fused.len(), 2);
+    }
+
+    #[test]
+    fn fuse_rankings_respects_list_weight() {
+        let a_only = RankedList {
+            entries: vec![RankedEntry { note_id: "a".to_string(), rank: 1 }],
+            weight: Some(10.0),
+        };
+        let b_only = RankedList {
+            entries: vec![RankedEntry { note_id: "b".to_string(), rank: 1 }],
+            weight: Some(0.1),
+        };
+
+        let fused = fuse_rankings(vec![a_only, b_only], None);
+
+        assert_eq!(fused[0].note_id, "a");
+    }
+
+    #[test]
+    fn normalize_folds_diacritics_by_default() {
+        assert_eq!(normalize("café", false), normalize("cafe", false));
+    }
+
+    #[test]
+    fn normalize_keeps_diacritics_when_accent_sensitive() {
+        assert_ne!(normalize("café", true), normalize("cafe", true));
+    }
+
+    #[test]
+    fn case_match_penalty_is_zero_for_exact_case() {
+        let raw_chars: Vec<char> = "Foo".chars().collect();
+        let positions = vec![0, 1, 2];
+
+        let penalty = case_match_penalty("Foo", &raw_chars, &positions, false);
+        assert_eq!(penalty, Some(0.0));
+    }
+
+    #[test]
+    fn case_match_penalty_applies_small_penalty_when_case_folded() {
+        let raw_chars: Vec<char> = "Foo".chars().collect();
+        let positions = vec![0, 1, 2];
+
+        let penalty = case_match_penalty("foo", &raw_chars, &positions, false);
+        assert_eq!(penalty, Some(CASE_MISMATCH_PENALTY));
+    }
+
+    #[test]
+    fn case_match_penalty_rejects_when_case_sensitive() {
+        let raw_chars: Vec<char> = "Foo".chars().collect();
+        let positions = vec![0, 1, 2];
+
+        let penalty = case_match_penalty("foo", &raw_chars, &positions, true);
+        assert_eq!(penalty, None);
+    }
+}